@@ -0,0 +1,113 @@
+//! Paragraph itemization: runs the Unicode Bidirectional Algorithm over a
+//! paragraph, splits it into directional runs, resolves each run's script
+//! and font, and reorders the runs into visual order — so mixed LTR/RTL
+//! text (e.g. Arabic with embedded Latin or digits) advances correctly
+//! instead of relying on the caller hard-coding one direction for the
+//! whole string.
+
+use std::ops::Range;
+
+use swash::shape::Direction;
+use swash::text::{analyze, Script};
+use unicode_bidi::{BidiInfo, Level};
+
+use crate::fallback::FontStack;
+
+/// A maximal span of text sharing one direction, one resolved script and
+/// one font, already placed in visual (left-to-right-on-screen) order.
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub range: Range<usize>,
+    pub script: Script,
+    pub direction: Direction,
+    pub font_index: usize,
+}
+
+/// Runs the Unicode Bidirectional Algorithm over `text` (with `base_direction`
+/// as the paragraph's default), itemizes the result into script/font runs,
+/// and reorders them into visual order, ready to feed into [`shape_str`](crate::fallback::shape_str)
+/// one run at a time.
+pub fn layout_paragraph(text: &str, base_direction: Direction, stack: &FontStack) -> Vec<Run> {
+    let base_level = match base_direction {
+        Direction::LeftToRight => Level::ltr(),
+        Direction::RightToLeft => Level::rtl(),
+    };
+
+    let bidi_info = BidiInfo::new(text, Some(base_level));
+
+    let mut runs = Vec::new();
+
+    for paragraph in &bidi_info.paragraphs {
+        let line = paragraph.range.clone();
+        let (levels, level_runs) = bidi_info.visual_runs(paragraph, line);
+
+        for level_run in level_runs {
+            let direction = if levels[level_run.start].is_rtl() {
+                Direction::RightToLeft
+            } else {
+                Direction::LeftToRight
+            };
+
+            // A single bidi run can still mix scripts (e.g. Latin digits
+            // inside an Arabic run), so split it further at script/font
+            // boundaries before handing it off to the shaper.
+            runs.extend(split_run(text, level_run, direction, stack));
+        }
+    }
+
+    runs
+}
+
+/// Splits `range` into maximal sub-runs that each share one resolved
+/// script and one font from `stack`.
+fn split_run(text: &str, range: Range<usize>, direction: Direction, stack: &FontStack) -> Vec<Run> {
+    let mut runs = Vec::new();
+    let mut run_start = range.start;
+    let mut current: Option<(Script, usize)> = None;
+
+    for (offset, ch) in text[range.clone()].char_indices() {
+        let byte_idx = range.start + offset;
+        let script = char_script(ch);
+        let font_index = stack.resolve_font(ch);
+
+        match current {
+            Some((s, f)) if s == script && f == font_index => {}
+            Some((prev_script, prev_font_index)) => {
+                runs.push(Run {
+                    range: run_start..byte_idx,
+                    script: prev_script,
+                    direction,
+                    font_index: prev_font_index,
+                });
+                run_start = byte_idx;
+                current = Some((script, font_index));
+            }
+            None => current = Some((script, font_index)),
+        }
+    }
+
+    if let Some((script, font_index)) = current {
+        runs.push(Run {
+            range: run_start..range.end,
+            script,
+            direction,
+            font_index,
+        });
+    }
+
+    // Sub-runs above are collected in logical (source) byte order; for an
+    // RTL level run that's the mirror of visual order, since the run as a
+    // whole is already placed right-to-left on screen.
+    if direction == Direction::RightToLeft {
+        runs.reverse();
+    }
+
+    runs
+}
+
+fn char_script(ch: char) -> Script {
+    analyze(std::iter::once(ch))
+        .next()
+        .map(|(props, _)| props.script())
+        .unwrap_or(Script::Unknown)
+}
@@ -0,0 +1,119 @@
+//! Automatic font fallback: shape text against an ordered [`FontStack`]
+//! instead of a single hard-coded font, falling back down the stack for
+//! characters the primary font can't cover.
+
+use swash::shape::cluster::Glyph;
+use swash::shape::{Direction, ShapeContext};
+use swash::text::Script;
+
+use crate::font::Font;
+
+/// An ordered list of fonts to consult when shaping text. The first font
+/// whose charmap covers a character wins; later fonts are only used as
+/// fallback for characters the earlier ones map to `.notdef` (glyph id 0).
+pub struct FontStack {
+    fonts: Vec<Font>,
+}
+
+impl FontStack {
+    pub fn new(fonts: Vec<Font>) -> Self {
+        assert!(!fonts.is_empty(), "a font stack needs at least one font");
+        Self { fonts }
+    }
+
+    pub fn font(&self, index: usize) -> &Font {
+        &self.fonts[index]
+    }
+
+    /// Index of the first font in the stack whose charmap covers `ch`,
+    /// falling back to the primary font (index 0) if none do.
+    fn resolve(&self, ch: char) -> usize {
+        self.fonts
+            .iter()
+            .position(|font| font.charmap().map(ch) != 0)
+            .unwrap_or(0)
+    }
+
+    /// Public entry point for [`crate::bidi`]'s run itemization, which
+    /// needs to split on font boundaries the same way `shape_str` does.
+    pub(crate) fn resolve_font(&self, ch: char) -> usize {
+        self.resolve(ch)
+    }
+}
+
+/// A shaped glyph tagged with the index, into the originating [`FontStack`],
+/// of the font it was shaped with, so the correct `Scaler` can be picked at
+/// rasterization time.
+#[derive(Debug, Clone, Copy)]
+pub struct StackGlyph {
+    pub glyph: Glyph,
+    pub font_index: usize,
+}
+
+/// Shapes `text` against `stack`: segments the input into maximal runs
+/// covered by a single font (falling back per character via [`FontStack::resolve`],
+/// before shaping, so the characters of one cluster can resolve to different
+/// fonts), builds a shaper per run from one shared `ShapeContext`, and yields
+/// a unified glyph stream recording which font produced each glyph. No
+/// `ScaleContext` is built here; the caller picks the `Scaler` from each
+/// glyph's `font_index`.
+pub fn shape_str(
+    stack: &FontStack,
+    text: &str,
+    script: Script,
+    direction: Direction,
+    font_size: f32,
+) -> Vec<StackGlyph> {
+    let mut shape_ctx = ShapeContext::new();
+    let mut glyphs = Vec::new();
+
+    for (run, font_index) in segment_runs(stack, text) {
+        let font = stack.font(font_index);
+
+        let mut shaper = shape_ctx
+            .builder(font.as_ref())
+            .script(script)
+            .direction(direction)
+            .size(font_size)
+            .build();
+
+        shaper.add_str(run);
+
+        shaper.shape_with(|cluster| {
+            glyphs.extend(cluster.glyphs.iter().map(|glyph| StackGlyph {
+                glyph: *glyph,
+                font_index,
+            }));
+        });
+    }
+
+    glyphs
+}
+
+/// Splits `text` into `(substring, font_index)` runs, each maximal and
+/// covered entirely by one font in the stack.
+fn segment_runs<'a>(stack: &FontStack, text: &'a str) -> Vec<(&'a str, usize)> {
+    let mut runs = Vec::new();
+    let mut run_start = 0;
+    let mut run_font = None;
+
+    for (byte_idx, ch) in text.char_indices() {
+        let font_index = stack.resolve(ch);
+
+        match run_font {
+            Some(current) if current == font_index => {}
+            Some(current) => {
+                runs.push((&text[run_start..byte_idx], current));
+                run_start = byte_idx;
+                run_font = Some(font_index);
+            }
+            None => run_font = Some(font_index),
+        }
+    }
+
+    if let Some(current) = run_font {
+        runs.push((&text[run_start..], current));
+    }
+
+    runs
+}
@@ -0,0 +1,60 @@
+//! Font loading. A [`Font`] owns the raw file bytes and keeps swash's
+//! [`CacheKey`] around so that transient [`FontRef`]s can be produced
+//! cheaply without invalidating any caches keyed on it.
+
+use swash::{Attributes, CacheKey, Charmap, FontRef};
+
+pub struct Font {
+    /// Full content of the font file
+    data: Vec<u8>,
+    /// Offset to the table directory
+    offset: u32,
+    /// Cache key
+    key: CacheKey,
+}
+
+impl Font {
+    pub fn from_file(path: &str, index: usize) -> Option<Self> {
+        // Read the full font file
+        let data = std::fs::read(path).ok()?;
+
+        // Create a temporary font reference for the font at `index` in the file.
+        // This will do some basic validation, compute the necessary offset
+        // and generate a fresh cache key for us.
+        let font = FontRef::from_index(&data, index)?;
+        let (offset, key) = (font.offset, font.key);
+
+        // Return our struct with the original file data and copies of the
+        // offset and key from the font reference
+        Some(Self { data, offset, key })
+    }
+
+    // As a convenience, you may want to forward some methods.
+    pub fn attributes(&self) -> Attributes {
+        self.as_ref().attributes()
+    }
+
+    pub fn charmap(&self) -> Charmap {
+        self.as_ref().charmap()
+    }
+
+    /// Create the transient font reference for accessing this crate's
+    /// functionality.
+    pub fn as_ref(&self) -> FontRef {
+        // Note that you'll want to initialize the struct directly here as
+        // using any of the FontRef constructors will generate a new key which,
+        // while completely safe, will nullify the performance optimizations of
+        // the caching mechanisms used in this crate.
+        FontRef {
+            data: &self.data,
+            offset: self.offset,
+            key: self.key,
+        }
+    }
+
+    /// Cache key identifying this font, used to key [`GlyphKey`](crate::glyph_cache::GlyphKey)s
+    /// across multiple fonts sharing one [`GlyphCache`](crate::glyph_cache::GlyphCache).
+    pub fn cache_key(&self) -> CacheKey {
+        self.key
+    }
+}
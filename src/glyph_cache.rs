@@ -0,0 +1,337 @@
+//! A small rasterization cache: glyphs are rasterized once per
+//! `(font, glyph id, size, subpixel position, antialias, style, color)`
+//! combination and packed into a shared atlas, so repeated glyphs (spaces,
+//! common letters, ...) are not rasterized again while their entry stays in
+//! the cache. An entry evicted by the LRU is rasterized again on its next
+//! request.
+
+use std::num::NonZeroUsize;
+use std::rc::Rc;
+
+use lru::LruCache;
+use swash::scale::image::Image;
+use swash::scale::Scaler;
+use swash::shape::cluster::Glyph;
+use swash::zeno::Vector;
+use swash::CacheKey;
+
+use crate::render::{self, Antialias, GammaLut, RenderedGlyph};
+use crate::style::{self, GlyphStyle, StyleKey};
+
+/// Number of fractional bits kept from a glyph's subpixel offset when
+/// building a [`GlyphKey`]. Enough to avoid visible snapping, but coarse
+/// enough that repeated glyphs at nearly the same position still hit the
+/// cache.
+const SUBPIXEL_BITS: u32 = 3;
+const SUBPIXEL_STEPS: u32 = 1 << SUBPIXEL_BITS;
+const SUBPIXEL_MASK: u8 = (SUBPIXEL_STEPS - 1) as u8;
+
+/// Identifies a single rasterized glyph instance: which font, which glyph
+/// id, at which size and (quantized) subpixel position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GlyphKey {
+    pub font: CacheKey,
+    pub glyph_id: u16,
+    /// Font size in 64ths of a pixel, so it can be hashed/compared exactly.
+    pub size_fixed: u32,
+    /// Quantized fractional `(x, y)` offset, packed as
+    /// `(x_bits << SUBPIXEL_BITS) | y_bits`.
+    pub subpixel_offset: u8,
+    /// Antialiasing mode the glyph was (or will be) rasterized with.
+    /// Rasterized bytes differ in shape between modes, so they must not
+    /// share a cache slot.
+    pub antialias: Antialias,
+    /// Synthetic styling and variation coordinates the glyph was (or will
+    /// be) rasterized with, so e.g. a synthetic-bold 'e' never collides
+    /// with a regular-weight 'e'.
+    pub style: StyleKey,
+    /// Whether this entry was (or will be) rasterized through
+    /// [`GlyphCache::get_or_rasterize_color`] rather than
+    /// [`GlyphCache::get_or_rasterize`]. Without this, a glyph rasterized
+    /// mask-only first and later requested in color (or vice versa) would
+    /// silently hand back the wrong cached entry instead of re-rendering.
+    pub wants_color: bool,
+}
+
+impl GlyphKey {
+    pub fn new(
+        font: CacheKey,
+        glyph: &Glyph,
+        font_size: f32,
+        antialias: Antialias,
+        style: StyleKey,
+    ) -> Self {
+        Self::with_color(font, glyph, font_size, antialias, style, false)
+    }
+
+    /// Same as [`Self::new`], but for use with
+    /// [`GlyphCache::get_or_rasterize_color`].
+    pub fn with_color(
+        font: CacheKey,
+        glyph: &Glyph,
+        font_size: f32,
+        antialias: Antialias,
+        style: StyleKey,
+        wants_color: bool,
+    ) -> Self {
+        let quantize = |f: f32| (f.rem_euclid(1.0) * SUBPIXEL_STEPS as f32) as u8 & SUBPIXEL_MASK;
+
+        Self {
+            font,
+            glyph_id: glyph.id,
+            size_fixed: (font_size * 64.0).round() as u32,
+            subpixel_offset: (quantize(glyph.x) << SUBPIXEL_BITS) | quantize(glyph.y),
+            antialias,
+            style,
+            wants_color,
+        }
+    }
+
+    /// Reconstructs the quantized fractional offset to hand back to swash's
+    /// renderer on a cache miss.
+    pub fn offset(&self) -> Vector {
+        let x_bits = (self.subpixel_offset >> SUBPIXEL_BITS) & SUBPIXEL_MASK;
+        let y_bits = self.subpixel_offset & SUBPIXEL_MASK;
+        Vector::new(
+            x_bits as f32 / SUBPIXEL_STEPS as f32,
+            y_bits as f32 / SUBPIXEL_STEPS as f32,
+        )
+    }
+}
+
+/// Location of a cached glyph's bitmap inside the atlas texture, plus the
+/// placement metadata swash reported when it was rasterized.
+///
+/// Color glyphs (COLR/CBDT/SVG emoji) don't share the atlas' fixed
+/// bytes-per-pixel layout with the rest of a cache's coverage masks, so
+/// their RGBA bytes are kept alongside the entry instead of being packed
+/// into it; `x`/`y` are meaningless in that case.
+#[derive(Debug, Clone)]
+pub struct CachedGlyph {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub left: i32,
+    pub top: i32,
+    /// Straight-alpha RGBA8 bytes (`width * height * 4`) when this entry is
+    /// a color glyph, `None` for an ordinary coverage mask living in the
+    /// atlas.
+    pub color: Option<Rc<[u8]>>,
+}
+
+/// Padding (in pixels) kept around every glyph bitmap inside the atlas, to
+/// stop neighbouring glyphs from bleeding into each other under bilinear
+/// sampling.
+const ATLAS_PADDING: u32 = 1;
+
+/// Shelf atlas: glyphs are packed left-to-right into shelves that grow
+/// downward, the simplest packing scheme that still keeps the texture
+/// reasonably dense for left-to-right text. Stores `bytes_per_pixel` bytes
+/// per texel, so it can hold either single-channel coverage or per-channel
+/// subpixel coverage.
+struct ShelfAtlas {
+    width: u32,
+    height: u32,
+    bytes_per_pixel: u32,
+    data: Vec<u8>,
+    cursor_x: u32,
+    shelf_y: u32,
+    shelf_height: u32,
+}
+
+impl ShelfAtlas {
+    fn new(width: u32, bytes_per_pixel: u32) -> Self {
+        Self {
+            width,
+            height: 0,
+            bytes_per_pixel,
+            data: Vec::new(),
+            cursor_x: 0,
+            shelf_y: 0,
+            shelf_height: 0,
+        }
+    }
+
+    fn grow_to(&mut self, height: u32) {
+        if height > self.height {
+            self.data
+                .resize(height as usize * self.width as usize * self.bytes_per_pixel as usize, 0);
+            self.height = height;
+        }
+    }
+
+    /// Reserves space for a `width x height` bitmap, starting a new shelf if
+    /// the current one has run out of room, and returns where to blit it.
+    /// Returns `None` if the bitmap (with padding) is wider than the atlas
+    /// itself, since no shelf could ever fit it.
+    fn alloc(&mut self, width: u32, height: u32) -> Option<CachedGlyph> {
+        let padded_width = width + ATLAS_PADDING * 2;
+        let padded_height = height + ATLAS_PADDING * 2;
+
+        if padded_width > self.width {
+            return None;
+        }
+
+        if self.cursor_x + padded_width > self.width {
+            self.shelf_y += self.shelf_height;
+            self.shelf_height = 0;
+            self.cursor_x = 0;
+        }
+
+        self.grow_to(self.shelf_y + padded_height);
+        self.shelf_height = self.shelf_height.max(padded_height);
+
+        let x = self.cursor_x + ATLAS_PADDING;
+        let y = self.shelf_y + ATLAS_PADDING;
+        self.cursor_x += padded_width;
+
+        Some(CachedGlyph {
+            x,
+            y,
+            width,
+            height,
+            left: 0,
+            top: 0,
+            color: None,
+        })
+    }
+
+    fn blit(&mut self, glyph: &CachedGlyph, pixels: &[u8]) {
+        let bpp = self.bytes_per_pixel as usize;
+        let row_bytes = glyph.width as usize * bpp;
+
+        for row in 0..glyph.height as usize {
+            let src = &pixels[row * row_bytes..][..row_bytes];
+            let dst_start = ((glyph.y as usize + row) * self.width as usize + glyph.x as usize) * bpp;
+            self.data[dst_start..dst_start + row_bytes].copy_from_slice(src);
+        }
+    }
+}
+
+/// Rasterizes glyphs on demand and caches the results in a shared atlas,
+/// evicting the least-recently-used entry once `capacity` is exceeded.
+///
+/// A single cache only ever holds one antialiasing mode's worth of glyphs,
+/// since the atlas' texel layout (1 byte of coverage vs. 4 for subpixel)
+/// depends on it; mixing modes belongs in two separate caches.
+pub struct GlyphCache {
+    entries: LruCache<GlyphKey, CachedGlyph>,
+    atlas: ShelfAtlas,
+    antialias: Antialias,
+    gamma_lut: GammaLut,
+}
+
+impl GlyphCache {
+    pub fn new(capacity: usize, atlas_width: u32, antialias: Antialias) -> Self {
+        Self {
+            entries: LruCache::new(NonZeroUsize::new(capacity).expect("capacity must be > 0")),
+            atlas: ShelfAtlas::new(atlas_width, antialias.bytes_per_pixel()),
+            antialias,
+            gamma_lut: GammaLut::default(),
+        }
+    }
+
+    pub fn atlas_data(&self) -> &[u8] {
+        &self.atlas.data
+    }
+
+    pub fn atlas_width(&self) -> u32 {
+        self.atlas.width
+    }
+
+    pub fn antialias(&self) -> Antialias {
+        self.antialias
+    }
+
+    /// Returns the cached glyph for `key`, rasterizing via `scaler` and
+    /// inserting into the atlas on a cache miss. `style` must be the same
+    /// style `key.style` was built from.
+    pub fn get_or_rasterize(
+        &mut self,
+        key: GlyphKey,
+        scaler: &mut Scaler,
+        style: &GlyphStyle,
+    ) -> Option<CachedGlyph> {
+        if let Some(glyph) = self.entries.get(&key) {
+            return Some(glyph.clone());
+        }
+
+        let image = render::rasterize_transformed(
+            scaler,
+            key.glyph_id,
+            key.offset(),
+            style.transform(),
+            key.antialias,
+            &self.gamma_lut,
+        )?;
+
+        let glyph = self.pack_mask(image, style)?;
+        self.entries.put(key, glyph.clone());
+        Some(glyph)
+    }
+
+    /// Emboldens (if requested) and packs a coverage-mask `image` into the
+    /// atlas, returning its placement, or `None` if the glyph is wider than
+    /// the atlas itself and can never be packed. Shared by
+    /// [`Self::get_or_rasterize`] and [`Self::get_or_rasterize_color`]'s
+    /// mask fallback.
+    fn pack_mask(&mut self, mut image: Image, style: &GlyphStyle) -> Option<CachedGlyph> {
+        if style.embolden_px > 0.0 {
+            style::embolden(
+                &mut image.data,
+                image.placement.width as usize,
+                image.placement.height as usize,
+                self.antialias.bytes_per_pixel() as usize,
+                style.embolden_px.round() as u32,
+            );
+        }
+
+        let mut glyph = self.atlas.alloc(image.placement.width, image.placement.height)?;
+        self.atlas.blit(&glyph, &image.data);
+        glyph.left = image.placement.left;
+        glyph.top = image.placement.top;
+        Some(glyph)
+    }
+
+    /// Same as [`get_or_rasterize`](Self::get_or_rasterize), but for glyphs
+    /// that may carry color (emoji, COLR fonts): a `Content::Color` result
+    /// bypasses the atlas entirely, since its RGBA bytes don't fit the
+    /// atlas' mask-only `bytes_per_pixel` layout, and is kept on the cache
+    /// entry instead.
+    pub fn get_or_rasterize_color(
+        &mut self,
+        key: GlyphKey,
+        scaler: &mut Scaler,
+        style: &GlyphStyle,
+    ) -> Option<CachedGlyph> {
+        if let Some(glyph) = self.entries.get(&key) {
+            return Some(glyph.clone());
+        }
+
+        let rendered = render::rasterize_color(
+            scaler,
+            key.glyph_id,
+            key.offset(),
+            style.transform(),
+            key.antialias,
+            &self.gamma_lut,
+        )?;
+
+        let glyph = match rendered {
+            RenderedGlyph::Color(image) => CachedGlyph {
+                x: 0,
+                y: 0,
+                width: image.placement.width,
+                height: image.placement.height,
+                left: image.placement.left,
+                top: image.placement.top,
+                color: Some(Rc::from(image.data)),
+            },
+            RenderedGlyph::Mask(image) => self.pack_mask(image, style)?,
+        };
+
+        self.entries.put(key, glyph.clone());
+        Some(glyph)
+    }
+}
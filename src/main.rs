@@ -1,78 +1,26 @@
+mod bidi;
+mod fallback;
+mod font;
+mod glyph_cache;
+mod outline;
+mod parallel;
+mod render;
+mod style;
+
+use bidi::layout_paragraph;
+use fallback::{shape_str, FontStack};
+use font::Font;
+use glyph_cache::{CachedGlyph, GlyphCache, GlyphKey};
 use image::{save_buffer_with_format, ColorType, ImageFormat};
-use swash::scale::image::Image;
-use swash::scale::{Render, ScaleContext, Scaler, Source, StrikeWith};
-use swash::shape::cluster::{Glyph, GlyphCluster};
+use outline::GlyphOutline;
+use parallel::{rasterize_batch, RasterRequest};
+use render::{Antialias, GammaLut};
+use style::{GlyphStyle, StyleKey};
+use swash::scale::ScaleContext;
+use swash::shape::cluster::GlyphCluster;
 use swash::shape::{Direction, ShapeContext};
 use swash::text::Script;
-use swash::{zeno, Attributes, CacheKey, Charmap, FontRef};
-
-pub struct Font {
-    /// Full content of the font file
-    data: Vec<u8>,
-    /// Offset to the table directory
-    offset: u32,
-    /// Cache key
-    key: CacheKey,
-}
-
-impl Font {
-    pub fn from_file(path: &str, index: usize) -> Option<Self> {
-        // Read the full font file
-        let data = std::fs::read(path).ok()?;
-
-        // Create a temporary font reference for the first font in the file.
-        // This will do some basic validation, compute the necessary offset
-        // and generate a fresh cache key for us.
-        let font = FontRef::from_index(&data, index)?;
-        let (offset, key) = (font.offset, font.key);
-
-        // Return our struct with the original file data and copies of the
-        // offset and key from the font reference
-        Some(Self { data, offset, key })
-    }
-
-    // As a convenience, you may want to forward some methods.
-    pub fn attributes(&self) -> Attributes {
-        self.as_ref().attributes()
-    }
-
-    pub fn charmap(&self) -> Charmap {
-        self.as_ref().charmap()
-    }
-
-    /// Create the transient font reference for accessing this crate's
-    /// functionality.
-    pub fn as_ref(&self) -> FontRef {
-        // Note that you'll want to initialize the struct directly here as
-        // using any of the FontRef constructors will generate a new key which,
-        // while completely safe, will nullify the performance optimizations of
-        // the caching mechanisms used in this crate.
-        FontRef {
-            data: &self.data,
-            offset: self.offset,
-            key: self.key,
-        }
-    }
-}
-
-fn render_glyph(scaler: &mut Scaler, glyph: &Glyph) -> Option<Image> {
-    use zeno::{Format, Vector};
-
-    // Compute the fractional offset-- you'll likely want to quantize this
-    // in a real renderer
-    let offset = Vector::new(glyph.x.fract(), glyph.y.fract());
-
-    // Render glyph into image (subpixel format = alpha)
-    // This will give us an image with only an alpha channel
-    Render::new(&[
-        Source::ColorOutline(0),
-        Source::ColorBitmap(StrikeWith::BestFit),
-        Source::Outline,
-    ])
-    .format(Format::Alpha)
-    .offset(offset)
-    .render(scaler, glyph.id)
-}
+use swash::zeno::Vector;
 
 fn main() {
     let roboto = Font::from_file("Roboto-Regular.ttf", 0).unwrap();
@@ -81,7 +29,7 @@ fn main() {
         Font::from_file("/usr/share/fonts/noto/NotoNaskhArabic-Regular.ttf", 0).unwrap();
 
     let mut glyphs = Vec::new();
-    let mut glyph_images = Vec::new();
+    let mut cached_glyphs: Vec<CachedGlyph> = Vec::new();
 
     let font_size = 64.;
     let hint = false;
@@ -92,6 +40,20 @@ fn main() {
     // Scale context to turn glyphs into images
     let mut scale_ctx = ScaleContext::new();
 
+    // Antialiasing mode shared by every glyph rasterized below. Subpixel
+    // gives sharper text on LCD panels at the cost of 4x the atlas memory;
+    // swap to `Antialias::Grayscale` to fall back to the old behaviour.
+    let antialias = Antialias::Subpixel;
+
+    // Rasterization cache shared across every font below: the key embeds the
+    // font's `CacheKey`, so entries from different fonts never collide.
+    let mut glyph_cache = GlyphCache::new(256, 1024, antialias);
+
+    // Default (unstyled) glyph style shared by the plain-text blocks below;
+    // only the synthetic-bold/oblique demo further down builds a non-default one.
+    let plain_style = GlyphStyle::default();
+    let plain_style_key = StyleKey::new(&plain_style);
+
     {
         let mut scaler = scale_ctx
             .builder(roboto.as_ref())
@@ -107,13 +69,118 @@ fn main() {
 
         roboto_shaper.add_str("a quick brown fox?   ");
 
+        // Vector outline of the letter 'a', tessellated into a triangle
+        // mesh: unlike the atlas bitmaps above, this geometry can be
+        // uploaded once and reused at any size.
+        if let Some(outline) = GlyphOutline::extract(&mut scaler, roboto.charmap().map('a')) {
+            let mesh = outline.tessellate(0.1);
+            println!(
+                "outline for 'a': {} commands, {} vertices, {} triangles",
+                outline.commands.len(),
+                mesh.vertices.len(),
+                mesh.indices.len() / 3
+            );
+
+            let min_x = mesh.vertices.iter().map(|v| v.x).fold(f32::INFINITY, f32::min);
+            let min_y = mesh.vertices.iter().map(|v| v.y).fold(f32::INFINITY, f32::min);
+            let max_x = mesh.vertices.iter().map(|v| v.x).fold(f32::NEG_INFINITY, f32::max);
+            let max_y = mesh.vertices.iter().map(|v| v.y).fold(f32::NEG_INFINITY, f32::max);
+            println!("outline for 'a' bounds: ({min_x}, {min_y}) to ({max_x}, {max_y})");
+        }
+
+        // `Antialias::Mono` thresholds coverage to fully on/off instead of
+        // grayscale or subpixel blending, for callers that just want a
+        // crisp 1-bit mask.
+        if let Some(mono) = render::rasterize(
+            &mut scaler,
+            roboto.charmap().map('a'),
+            Vector::ZERO,
+            Antialias::Mono,
+            &GammaLut::default(),
+        ) {
+            println!(
+                "mono-rasterized 'a': {}x{}",
+                mono.placement.width, mono.placement.height
+            );
+        }
+
+        let mut roboto_glyphs = Vec::new();
+
         // Start shapin
         roboto_shaper.shape_with(|glyph_cluster: &GlyphCluster| {
             glyphs.extend_from_slice(glyph_cluster.glyphs);
+            roboto_glyphs.extend_from_slice(glyph_cluster.glyphs);
+
+            cached_glyphs.extend(glyph_cluster.glyphs.iter().filter_map(|glyph| {
+                let key = GlyphKey::new(roboto.cache_key(), glyph, font_size, antialias, plain_style_key);
+                glyph_cache.get_or_rasterize(key, &mut scaler, &plain_style)
+            }));
+        });
+
+        // Same glyphs, rasterized through the batched/parallel path instead
+        // of one at a time: duplicates (spaces, repeated letters) collapse
+        // to a single rasterization job.
+        let requests: Vec<RasterRequest> = roboto_glyphs
+            .iter()
+            .map(|glyph| RasterRequest {
+                key: GlyphKey::new(roboto.cache_key(), glyph, font_size, antialias, plain_style_key),
+                font: roboto.as_ref(),
+                font_size,
+                hint,
+            })
+            .collect();
+
+        let batch = rasterize_batch(&requests, antialias);
+        println!(
+            "batch-rasterized {} unique glyphs out of {} requests",
+            batch.len(),
+            requests.len()
+        );
+    };
+
+    {
+        // Roboto only ships a regular weight and no italic; synthetic
+        // bold/oblique fake both from it instead of requiring a matching
+        // font file on disk, and a `wght` variation is forwarded to the
+        // shaper/scaler in case the face happens to be variable.
+        println!("roboto attributes: {:?}", roboto.attributes());
+
+        let bold_oblique = GlyphStyle {
+            oblique_degrees: 12.0,
+            embolden_px: 1.0,
+            variations: vec![("wght", 700.0).into()],
+        };
+        let bold_oblique_key = StyleKey::new(&bold_oblique);
+
+        let mut styled_scale_ctx = ScaleContext::new();
+        let mut scaler = styled_scale_ctx
+            .builder(roboto.as_ref())
+            .size(font_size)
+            .hint(hint)
+            .variations(bold_oblique.variations.iter().copied())
+            .build();
+
+        let mut styled_shaper = shape_ctx
+            .builder(roboto.as_ref())
+            .script(Script::Latin)
+            .size(font_size)
+            .variations(bold_oblique.variations.iter().copied())
+            .build();
+
+        styled_shaper.add_str("bold oblique");
+
+        styled_shaper.shape_with(|glyph_cluster: &GlyphCluster| {
+            glyphs.extend_from_slice(glyph_cluster.glyphs);
 
-            glyph_images.extend((glyph_cluster.glyphs.iter()).filter_map(|glyph| {
-                // render each glyph individually
-                render_glyph(&mut scaler, glyph)
+            cached_glyphs.extend(glyph_cluster.glyphs.iter().filter_map(|glyph| {
+                let key = GlyphKey::new(
+                    roboto.cache_key(),
+                    glyph,
+                    font_size,
+                    antialias,
+                    bold_oblique_key,
+                );
+                glyph_cache.get_or_rasterize(key, &mut scaler, &bold_oblique)
             }));
         });
     };
@@ -137,9 +204,9 @@ fn main() {
         noto_cjk_shaper.shape_with(|glyph_cluster: &GlyphCluster| {
             glyphs.extend_from_slice(glyph_cluster.glyphs);
 
-            glyph_images.extend((glyph_cluster.glyphs.iter()).filter_map(|glyph| {
-                // render each glyph individually
-                render_glyph(&mut scaler, glyph)
+            cached_glyphs.extend(glyph_cluster.glyphs.iter().filter_map(|glyph| {
+                let key = GlyphKey::new(noto_cjk.cache_key(), glyph, font_size, antialias, plain_style_key);
+                glyph_cache.get_or_rasterize(key, &mut scaler, &plain_style)
             }));
         });
     };
@@ -170,46 +237,216 @@ fn main() {
             println!("{} glyphs", glyph_cluster.glyphs.len());
             glyphs.extend_from_slice(glyph_cluster.glyphs);
 
-            glyph_images.extend((glyph_cluster.glyphs.iter()).filter_map(|glyph| {
-                // render each glyph individually
-                render_glyph(&mut scaler, glyph)
+            cached_glyphs.extend(glyph_cluster.glyphs.iter().filter_map(|glyph| {
+                let key = GlyphKey::new(noto_arab.cache_key(), glyph, font_size, antialias, plain_style_key);
+                glyph_cache.get_or_rasterize(key, &mut scaler, &plain_style)
             }));
         });
 
         println!("total glyphs: {n_glyphs}");
     };
 
+    {
+        // Roboto has no CJK coverage, so shaping this string against it
+        // alone would produce a run of `.notdef` boxes. A `FontStack`
+        // resolves each character against the first font that can cover it,
+        // falling back from Roboto to the CJK face automatically.
+        let font_stack = FontStack::new(vec![roboto, noto_cjk]);
+
+        let mut stack_scale_ctxs: Vec<ScaleContext> =
+            (0..2).map(|_| ScaleContext::new()).collect();
+
+        let mut scalers: Vec<_> = stack_scale_ctxs
+            .iter_mut()
+            .enumerate()
+            .map(|(i, ctx)| {
+                ctx.builder(font_stack.font(i).as_ref())
+                    .size(font_size)
+                    .hint(hint)
+                    .build()
+            })
+            .collect();
+
+        let stack_glyphs = shape_str(
+            &font_stack,
+            "café 世界  ",
+            Script::Latin,
+            Direction::LeftToRight,
+            font_size,
+        );
+
+        glyphs.extend(stack_glyphs.iter().map(|stack_glyph| stack_glyph.glyph));
+
+        cached_glyphs.extend(stack_glyphs.iter().filter_map(|stack_glyph| {
+            let font = font_stack.font(stack_glyph.font_index);
+            let key = GlyphKey::new(font.cache_key(), &stack_glyph.glyph, font_size, antialias, plain_style_key);
+            glyph_cache.get_or_rasterize(key, &mut scalers[stack_glyph.font_index], &plain_style)
+        }));
+    };
+
+    {
+        // Mixed-direction paragraph: Arabic text with an embedded Latin
+        // word and digits. `layout_paragraph` runs the Unicode
+        // Bidirectional Algorithm to split it into runs already in visual
+        // order, instead of the caller hard-coding one direction for the
+        // whole string the way the block above does. Noto Naskh Arabic has
+        // no Latin coverage, so a second font is on the stack for the
+        // embedded "Hello 2024"; `run.font_index` (already resolved per run
+        // by `layout_paragraph`) picks the matching scaler directly instead
+        // of re-resolving it per glyph.
+        let latin_fallback = Font::from_file("Roboto-Regular.ttf", 0).unwrap();
+        let font_stack = FontStack::new(vec![noto_arab, latin_fallback]);
+
+        let mut bidi_scale_ctxs: Vec<ScaleContext> = (0..2).map(|_| ScaleContext::new()).collect();
+
+        let mut bidi_scalers: Vec<_> = bidi_scale_ctxs
+            .iter_mut()
+            .enumerate()
+            .map(|(i, ctx)| {
+                ctx.builder(font_stack.font(i).as_ref())
+                    .size(font_size)
+                    .hint(hint)
+                    .build()
+            })
+            .collect();
+
+        let paragraph = "مرحبا Hello 2024 بالعالم";
+        let runs = layout_paragraph(paragraph, Direction::RightToLeft, &font_stack);
+
+        for run in &runs {
+            let run_text = &paragraph[run.range.clone()];
+            let stack_glyphs = shape_str(&font_stack, run_text, run.script, run.direction, font_size);
+            let font = font_stack.font(run.font_index);
+            let scaler = &mut bidi_scalers[run.font_index];
+
+            glyphs.extend(stack_glyphs.iter().map(|stack_glyph| stack_glyph.glyph));
+
+            cached_glyphs.extend(stack_glyphs.iter().filter_map(|stack_glyph| {
+                let key = GlyphKey::new(font.cache_key(), &stack_glyph.glyph, font_size, antialias, plain_style_key);
+                glyph_cache.get_or_rasterize(key, scaler, &plain_style)
+            }));
+        }
+    };
+
+    {
+        // Color glyphs (COLR/CBDT/SVG emoji) mixed with plain text:
+        // `rasterize`/`rasterize_transformed` always treat the image as
+        // coverage, which is fine for plain text but mangles mixed
+        // emoji+text strings down to monochrome boxes. `get_or_rasterize_color`
+        // checks whether swash actually rendered a color table and hands
+        // back real RGBA for the glyphs that have one. The emoji face has
+        // no Latin coverage of its own, so it rides the same `FontStack`
+        // fallback used above to keep the surrounding letters legible.
+        let roboto_text = Font::from_file("Roboto-Regular.ttf", 0).unwrap();
+        let noto_emoji = Font::from_file("/usr/share/fonts/noto/NotoColorEmoji.ttf", 0).unwrap();
+        let emoji_font_stack = FontStack::new(vec![roboto_text, noto_emoji]);
+
+        let mut emoji_scale_ctxs: Vec<ScaleContext> = (0..2).map(|_| ScaleContext::new()).collect();
+
+        let mut emoji_scalers: Vec<_> = emoji_scale_ctxs
+            .iter_mut()
+            .enumerate()
+            .map(|(i, ctx)| {
+                ctx.builder(emoji_font_stack.font(i).as_ref())
+                    .size(font_size)
+                    .hint(hint)
+                    .build()
+            })
+            .collect();
+
+        let stack_glyphs = shape_str(
+            &emoji_font_stack,
+            "fox 🦊 jumps 🚀  ",
+            Script::Latin,
+            Direction::LeftToRight,
+            font_size,
+        );
+
+        glyphs.extend(stack_glyphs.iter().map(|stack_glyph| stack_glyph.glyph));
+
+        cached_glyphs.extend(stack_glyphs.iter().filter_map(|stack_glyph| {
+            let font = emoji_font_stack.font(stack_glyph.font_index);
+            let key = GlyphKey::with_color(
+                font.cache_key(),
+                &stack_glyph.glyph,
+                font_size,
+                antialias,
+                plain_style_key,
+                true,
+            );
+            glyph_cache.get_or_rasterize_color(key, &mut emoji_scalers[stack_glyph.font_index], &plain_style)
+        }));
+    };
+
     // measure dimensions and baseline, and create image buffer
     let total_width: usize = (glyphs.iter()).map(|g| g.advance).sum::<f32>() as usize;
 
-    let baseline_height: usize = (glyph_images.iter())
-        .map(|glyph_img| glyph_img.placement.height as usize)
+    let baseline_height: usize = (cached_glyphs.iter())
+        .map(|glyph| glyph.height as usize)
         .max()
         .unwrap_or_default();
 
-    let total_height: usize = (glyph_images.iter())
-        .map(|glyph_img| {
-            glyph_img.placement.height as usize
-                + baseline_height.saturating_add_signed(-glyph_img.placement.top as isize)
+    let total_height: usize = (cached_glyphs.iter())
+        .map(|glyph| {
+            glyph.height as usize + baseline_height.saturating_add_signed(-glyph.top as isize)
         })
         .max()
         .unwrap_or_default();
 
     let mut img_buffer: Vec<[u8; 4]> = vec![[0, 0, 0, 0]; total_width * total_height];
 
-    // draw each glyph image in a loop
+    // draw each glyph, blitting out of the atlas, in a loop
+    let atlas_width = glyph_cache.atlas_width() as usize;
+    let atlas_bpp = glyph_cache.antialias().bytes_per_pixel() as usize;
+    let atlas_data = glyph_cache.atlas_data();
     let mut col = 0;
 
     let mut glyph_advance: usize = 0;
-    for (glyph_idx, (glyph_img, glyph)) in glyph_images.iter().zip(glyphs.iter()).enumerate() {
-        let width = glyph_img.placement.width as usize;
-        let height = glyph_img.placement.height as usize;
+    for (glyph_idx, (glyph, glyph_info)) in cached_glyphs.iter().zip(glyphs.iter()).enumerate() {
+        let width = glyph.width as usize;
+        let height = glyph.height as usize;
 
         if height == 0 {
             println!("Glyph #{} has height 0 (probably a space)", glyph_idx);
+        } else if let Some(color) = &glyph.color {
+            // Color glyph (emoji, COLR/CBDT/SVG): `color` is straight-alpha
+            // RGBA, so premultiply it and composite with a standard "over"
+            // blend instead of the coverage-into-channels path below, which
+            // would otherwise smear the glyph's own alpha across R/G/B.
+            let x_off = glyph.left as isize;
+            let y_off = baseline_height.saturating_add_signed(-glyph.top as isize);
+
+            for y in 0..usize::min(height, total_height) {
+                for x in 0..width {
+                    let x_buf = x.saturating_add_signed(x_off) + glyph_advance;
+                    let y_buf = y.saturating_add(y_off).min(total_height - 1);
+
+                    let buffer_idx = y_buf * total_width + x_buf;
+                    let color_idx = (y * width + x) * 4;
+                    let [cr, cg, cb, ca] = [
+                        color[color_idx],
+                        color[color_idx + 1],
+                        color[color_idx + 2],
+                        color[color_idx + 3],
+                    ];
+                    let [dr, dg, db, da] = img_buffer[buffer_idx];
+
+                    let src_a = ca as u16;
+                    let inv_a = 255 - src_a;
+                    let premultiply = |channel: u8| (channel as u16 * src_a / 255) as u8;
+                    let blend_dst = |channel: u8| (channel as u16 * inv_a / 255) as u8;
+
+                    img_buffer[buffer_idx] = [
+                        premultiply(cr).saturating_add(blend_dst(dr)),
+                        premultiply(cg).saturating_add(blend_dst(dg)),
+                        premultiply(cb).saturating_add(blend_dst(db)),
+                        ca.saturating_add(blend_dst(da)),
+                    ];
+                }
+            }
         } else {
-            let x_off = glyph_img.placement.left as isize;
-            let y_off = baseline_height.saturating_add_signed(-glyph_img.placement.top as isize);
+            let x_off = glyph.left as isize;
+            let y_off = baseline_height.saturating_add_signed(-glyph.top as isize);
 
             for y in 0..usize::min(height, total_height) {
                 for x in 0..width {
@@ -217,16 +454,32 @@ fn main() {
                     let y_buf = y.saturating_add(y_off).min(total_height - 1);
 
                     let buffer_idx = y_buf * total_width + x_buf;
-                    let glyph_idx = y * width + x;
+                    let atlas_idx =
+                        ((glyph.y as usize + y) * atlas_width + (glyph.x as usize + x)) * atlas_bpp;
 
                     let [r, g, b, a] = img_buffer[buffer_idx];
-                    let v = glyph_img.data[glyph_idx];
+
+                    // Subpixel coverage already carries distinct R/G/B
+                    // values (at zeno's 4-byte-per-texel stride, trailing
+                    // byte unused); grayscale/mono coverage is a single
+                    // channel smeared across all three, as before.
+                    let (vr, vg, vb) = if atlas_bpp == 4 {
+                        (
+                            atlas_data[atlas_idx],
+                            atlas_data[atlas_idx + 1],
+                            atlas_data[atlas_idx + 2],
+                        )
+                    } else {
+                        let v = atlas_data[atlas_idx];
+                        (v, v, v)
+                    };
+                    let a_cov = vr.max(vg).max(vb);
 
                     img_buffer[buffer_idx] = [
-                        v.saturating_add(r),
-                        v.saturating_add(g),
-                        v.saturating_add(b),
-                        v.saturating_add(a),
+                        vr.saturating_add(r),
+                        vg.saturating_add(g),
+                        vb.saturating_add(b),
+                        a_cov.saturating_add(a),
                     ];
 
                     if col & 0b001 > 0 {
@@ -244,7 +497,7 @@ fn main() {
             }
         }
 
-        glyph_advance += glyph.advance.round() as usize; // em_to_px(glyph.advance, &metrics);
+        glyph_advance += glyph_info.advance.round() as usize; // em_to_px(glyph.advance, &metrics);
         col = (col + 1) % 8;
     }
 
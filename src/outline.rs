@@ -0,0 +1,428 @@
+//! Vector glyph outlines and their tessellation into GPU-ready meshes.
+//!
+//! Bitmaps produced by [`crate::render::rasterize`] are resolution-dependent
+//! and have to be re-rendered at every size. An outline extracted here is
+//! scale-independent: a GPU-backed caller can upload the tessellated
+//! geometry once and transform it per size instead of maintaining a
+//! per-size atlas.
+
+use swash::scale::Scaler;
+use swash::zeno::{Command, PathData};
+
+/// One command in a glyph outline's path, in the units of the `Scaler` it
+/// was extracted with: pixels at the scaler's size, or font units when the
+/// scaler has no size set.
+#[derive(Debug, Clone, Copy)]
+pub enum PathCommand {
+    MoveTo(f32, f32),
+    LineTo(f32, f32),
+    QuadTo(f32, f32, f32, f32),
+    CurveTo(f32, f32, f32, f32, f32, f32),
+    Close,
+}
+
+/// A glyph's outline as a flat list of path commands.
+pub struct GlyphOutline {
+    pub commands: Vec<PathCommand>,
+}
+
+impl GlyphOutline {
+    /// Extracts `glyph_id`'s outline from `scaler`, or `None` if the glyph
+    /// has no scalable outline (e.g. a bitmap-only emoji glyph).
+    pub fn extract(scaler: &mut Scaler, glyph_id: u16) -> Option<Self> {
+        let outline = scaler.scale_outline(glyph_id)?;
+
+        let commands = outline
+            .path()
+            .commands()
+            .map(|command| match command {
+                Command::MoveTo(p) => PathCommand::MoveTo(p.x, p.y),
+                Command::LineTo(p) => PathCommand::LineTo(p.x, p.y),
+                Command::QuadTo(c, p) => PathCommand::QuadTo(c.x, c.y, p.x, p.y),
+                Command::CurveTo(c1, c2, p) => {
+                    PathCommand::CurveTo(c1.x, c1.y, c2.x, c2.y, p.x, p.y)
+                }
+                Command::Close => PathCommand::Close,
+            })
+            .collect();
+
+        Some(Self { commands })
+    }
+
+    /// Tessellates the outline into a triangle mesh: flattens curves to line
+    /// segments within `tolerance` (in the outline's units), bridges hole contours (the
+    /// inner counter of an 'a', 'o', 'e', ...) into their enclosing contour
+    /// so each filled region becomes one simple polygon, then ear-clips
+    /// every resulting polygon.
+    pub fn tessellate(&self, tolerance: f32) -> Mesh {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+
+        for polygon in merge_holes(flatten(&self.commands, tolerance)) {
+            if polygon.len() < 3 {
+                continue;
+            }
+
+            let base = vertices.len() as u16;
+            vertices.extend(polygon.iter().map(|&(x, y)| Vertex { x, y }));
+
+            for triangle in ear_clip(&polygon) {
+                indices.extend_from_slice(&[
+                    base + triangle[0] as u16,
+                    base + triangle[1] as u16,
+                    base + triangle[2] as u16,
+                ]);
+            }
+        }
+
+        Mesh { vertices, indices }
+    }
+}
+
+/// Bridges odd-depth (hole) contours into the contour that immediately
+/// encloses them, so e.g. an 'a' outline's outer contour plus its inner
+/// counter become one simple polygon instead of two separate ones whose
+/// naive triangulation would fill the counter solid.
+///
+/// Depth is "how many other contours contain this contour's first point",
+/// counted by even-odd nesting: even-depth contours are filled regions
+/// (the outer shape, or an island nested inside a hole), odd-depth ones are
+/// holes cut out of their immediate parent. Deepest holes are bridged
+/// first so a hole nested inside another hole's parent is resolved before
+/// its shallower ancestor.
+fn merge_holes(mut contours: Vec<Vec<(f32, f32)>>) -> Vec<Vec<(f32, f32)>> {
+    if contours.len() <= 1 {
+        return contours;
+    }
+
+    let depths = nesting_depths(&contours);
+    let mut order: Vec<usize> = (0..contours.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(depths[i]));
+
+    for i in order {
+        if depths[i].is_multiple_of(2) {
+            continue;
+        }
+
+        let probe = contours[i][0];
+        let parent = (0..contours.len())
+            .filter(|&j| j != i && depths[j] == depths[i] - 1 && point_in_polygon(probe, &contours[j]))
+            .min_by(|&a, &b| {
+                polygon_area(&contours[a])
+                    .abs()
+                    .partial_cmp(&polygon_area(&contours[b]).abs())
+                    .unwrap()
+            });
+
+        if let Some(parent) = parent {
+            let hole = std::mem::take(&mut contours[i]);
+            contours[parent] = bridge(&contours[parent], &hole);
+        }
+    }
+
+    contours
+        .into_iter()
+        .enumerate()
+        .filter(|(i, polygon)| depths[*i].is_multiple_of(2) && !polygon.is_empty())
+        .map(|(_, polygon)| polygon)
+        .collect()
+}
+
+/// For each contour, how many of the other contours contain its first
+/// point (even-odd nesting depth).
+fn nesting_depths(contours: &[Vec<(f32, f32)>]) -> Vec<usize> {
+    contours
+        .iter()
+        .enumerate()
+        .map(|(i, contour)| {
+            let probe = contour[0];
+            contours
+                .iter()
+                .enumerate()
+                .filter(|&(j, other)| j != i && point_in_polygon(probe, other))
+                .count()
+        })
+        .collect()
+}
+
+/// Ray-casting point-in-polygon test (even-odd rule), indifferent to
+/// winding direction.
+fn point_in_polygon(point: (f32, f32), polygon: &[(f32, f32)]) -> bool {
+    let mut inside = false;
+    let n = polygon.len();
+    let mut j = n - 1;
+
+    for i in 0..n {
+        let (xi, yi) = polygon[i];
+        let (xj, yj) = polygon[j];
+
+        if (yi > point.1) != (yj > point.1)
+            && point.0 < (xj - xi) * (point.1 - yi) / (yj - yi) + xi
+        {
+            inside = !inside;
+        }
+
+        j = i;
+    }
+
+    inside
+}
+
+/// Signed polygon area (shoelace formula); its sign gives the contour's
+/// winding direction.
+fn polygon_area(points: &[(f32, f32)]) -> f32 {
+    let n = points.len();
+    let mut area = 0.0;
+
+    for i in 0..n {
+        let (x0, y0) = points[i];
+        let (x1, y1) = points[(i + 1) % n];
+        area += x0 * y1 - x1 * y0;
+    }
+
+    area * 0.5
+}
+
+/// Connects `hole` to `outer` via a zero-area bridge edge between their
+/// closest vertex pair, producing one simple polygon a standard ear-clip
+/// triangulator can handle. `hole` is reversed first if needed so it winds
+/// opposite to `outer`, matching how a real hole faces the filled interior.
+fn bridge(outer: &[(f32, f32)], hole: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    let mut hole = hole.to_vec();
+    if polygon_area(outer).signum() == polygon_area(&hole).signum() {
+        hole.reverse();
+    }
+
+    let (oi, hi) = outer
+        .iter()
+        .enumerate()
+        .flat_map(|(oi, &op)| {
+            hole.iter()
+                .enumerate()
+                .map(move |(hi, &hp)| (oi, hi, dist_sq(op, hp)))
+        })
+        .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap())
+        .map(|(oi, hi, _)| (oi, hi))
+        .expect("outer and hole are both non-empty");
+
+    let mut merged = Vec::with_capacity(outer.len() + hole.len() + 2);
+    merged.extend_from_slice(&outer[..=oi]);
+    merged.extend_from_slice(&hole[hi..]);
+    merged.extend_from_slice(&hole[..=hi]);
+    merged.extend_from_slice(&outer[oi..]);
+    merged
+}
+
+fn dist_sq(a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (dx, dy) = (a.0 - b.0, a.1 - b.1);
+    dx * dx + dy * dy
+}
+
+/// Ear-clipping triangulation of a simple (possibly concave) polygon,
+/// returning triangles as index triples into `polygon`.
+fn ear_clip(polygon: &[(f32, f32)]) -> Vec<[usize; 3]> {
+    let n = polygon.len();
+    if n < 3 {
+        return Vec::new();
+    }
+
+    let mut indices: Vec<usize> = (0..n).collect();
+    if polygon_area(polygon) < 0.0 {
+        indices.reverse();
+    }
+
+    let mut triangles = Vec::new();
+    let mut guard = indices.len() * indices.len() + 8;
+
+    while indices.len() > 3 && guard > 0 {
+        guard -= 1;
+        let m = indices.len();
+        let mut clipped = false;
+
+        for i in 0..m {
+            let prev = indices[(i + m - 1) % m];
+            let cur = indices[i];
+            let next = indices[(i + 1) % m];
+
+            if is_ear(polygon, &indices, prev, cur, next) {
+                triangles.push([prev, cur, next]);
+                indices.remove(i);
+                clipped = true;
+                break;
+            }
+        }
+
+        if !clipped {
+            // Degenerate geometry (e.g. a zero-area bridge segment with no
+            // valid ear left); stop rather than loop forever.
+            break;
+        }
+    }
+
+    if indices.len() == 3 {
+        triangles.push([indices[0], indices[1], indices[2]]);
+    }
+
+    triangles
+}
+
+/// Whether `cur` (with neighbours `prev`/`next`) is a valid ear tip to clip:
+/// convex, and with none of the polygon's other remaining vertices inside
+/// the candidate triangle.
+fn is_ear(polygon: &[(f32, f32)], indices: &[usize], prev: usize, cur: usize, next: usize) -> bool {
+    let (a, b, c) = (polygon[prev], polygon[cur], polygon[next]);
+
+    if cross(a, b, c) <= 0.0 {
+        return false;
+    }
+
+    indices
+        .iter()
+        .all(|&p| p == prev || p == cur || p == next || !point_in_triangle(polygon[p], a, b, c))
+}
+
+fn cross(a: (f32, f32), b: (f32, f32), c: (f32, f32)) -> f32 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+fn point_in_triangle(p: (f32, f32), a: (f32, f32), b: (f32, f32), c: (f32, f32)) -> bool {
+    let side = |p1: (f32, f32), p2: (f32, f32), p3: (f32, f32)| {
+        (p1.0 - p3.0) * (p2.1 - p3.1) - (p2.0 - p3.0) * (p1.1 - p3.1)
+    };
+
+    let d1 = side(p, a, b);
+    let d2 = side(p, b, c);
+    let d3 = side(p, c, a);
+
+    let has_neg = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
+    let has_pos = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
+
+    !(has_neg && has_pos)
+}
+
+/// A single point in a tessellated mesh, in the outline's own units (see
+/// [`PathCommand`]).
+#[derive(Debug, Clone, Copy)]
+pub struct Vertex {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Triangle-list mesh produced by [`GlyphOutline::tessellate`].
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u16>,
+}
+
+/// Flattens a path into closed polyline contours, subdividing curves
+/// adaptively until they're within `tolerance` of a straight line.
+fn flatten(commands: &[PathCommand], tolerance: f32) -> Vec<Vec<(f32, f32)>> {
+    let mut contours = Vec::new();
+    let mut current = Vec::new();
+    let mut cursor = (0.0, 0.0);
+
+    for command in commands {
+        match *command {
+            PathCommand::MoveTo(x, y) => {
+                if !current.is_empty() {
+                    contours.push(std::mem::take(&mut current));
+                }
+                cursor = (x, y);
+                current.push(cursor);
+            }
+            PathCommand::LineTo(x, y) => {
+                cursor = (x, y);
+                current.push(cursor);
+            }
+            PathCommand::QuadTo(cx, cy, x, y) => {
+                flatten_quad(cursor, (cx, cy), (x, y), tolerance, 0, &mut current);
+                cursor = (x, y);
+            }
+            PathCommand::CurveTo(c1x, c1y, c2x, c2y, x, y) => {
+                flatten_cubic(cursor, (c1x, c1y), (c2x, c2y), (x, y), tolerance, 0, &mut current);
+                cursor = (x, y);
+            }
+            PathCommand::Close => {
+                if !current.is_empty() {
+                    contours.push(std::mem::take(&mut current));
+                }
+            }
+        }
+    }
+
+    if !current.is_empty() {
+        contours.push(current);
+    }
+
+    contours
+}
+
+/// Cap on recursive subdivision depth, in case `tolerance` is pathologically
+/// small.
+const MAX_SUBDIVISION_DEPTH: u32 = 16;
+
+fn flatten_quad(
+    p0: (f32, f32),
+    p1: (f32, f32),
+    p2: (f32, f32),
+    tolerance: f32,
+    depth: u32,
+    out: &mut Vec<(f32, f32)>,
+) {
+    if depth >= MAX_SUBDIVISION_DEPTH || point_to_segment_distance(p1, p0, p2) <= tolerance {
+        out.push(p2);
+        return;
+    }
+
+    let p01 = midpoint(p0, p1);
+    let p12 = midpoint(p1, p2);
+    let p012 = midpoint(p01, p12);
+
+    flatten_quad(p0, p01, p012, tolerance, depth + 1, out);
+    flatten_quad(p012, p12, p2, tolerance, depth + 1, out);
+}
+
+fn flatten_cubic(
+    p0: (f32, f32),
+    p1: (f32, f32),
+    p2: (f32, f32),
+    p3: (f32, f32),
+    tolerance: f32,
+    depth: u32,
+    out: &mut Vec<(f32, f32)>,
+) {
+    let flat = point_to_segment_distance(p1, p0, p3) <= tolerance
+        && point_to_segment_distance(p2, p0, p3) <= tolerance;
+
+    if depth >= MAX_SUBDIVISION_DEPTH || flat {
+        out.push(p3);
+        return;
+    }
+
+    let p01 = midpoint(p0, p1);
+    let p12 = midpoint(p1, p2);
+    let p23 = midpoint(p2, p3);
+    let p012 = midpoint(p01, p12);
+    let p123 = midpoint(p12, p23);
+    let p0123 = midpoint(p012, p123);
+
+    flatten_cubic(p0, p01, p012, p0123, tolerance, depth + 1, out);
+    flatten_cubic(p0123, p123, p23, p3, tolerance, depth + 1, out);
+}
+
+fn midpoint(a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
+    ((a.0 + b.0) * 0.5, (a.1 + b.1) * 0.5)
+}
+
+fn point_to_segment_distance(p: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len_sq = dx * dx + dy * dy;
+
+    if len_sq < f32::EPSILON {
+        return ((p.0 - a.0).powi(2) + (p.1 - a.1).powi(2)).sqrt();
+    }
+
+    // Distance from p to the infinite line through a-b; good enough for a
+    // flatness test without needing to clamp to the segment.
+    ((p.0 - a.0) * dy - (p.1 - a.1) * dx).abs() / len_sq.sqrt()
+}
@@ -0,0 +1,97 @@
+//! Batched, optionally-parallel glyph rasterization.
+//!
+//! [`crate::glyph_cache::GlyphCache`] rasterizes one glyph at a time as the
+//! shaper produces clusters. For large bodies of text the rasterization of
+//! *distinct* glyphs is embarrassingly parallel, so this module collects a
+//! batch of requests up front, deduplicates them by [`GlyphKey`], and
+//! rasterizes the unique set — across a rayon thread pool when the `rayon`
+//! feature is enabled, serially otherwise.
+
+use std::collections::{HashMap, HashSet};
+
+use swash::scale::image::Image;
+use swash::scale::ScaleContext;
+use swash::FontRef;
+
+use crate::glyph_cache::GlyphKey;
+use crate::render::{self, Antialias, GammaLut};
+
+/// One glyph to rasterize: its cache key plus everything needed to build a
+/// `Scaler` for it. The font reference and size travel with the request
+/// (rather than a pre-built `Scaler`) because `Scaler` isn't `Send` and
+/// can't cross the thread pool.
+pub struct RasterRequest<'a> {
+    pub key: GlyphKey,
+    pub font: FontRef<'a>,
+    pub font_size: f32,
+    pub hint: bool,
+}
+
+/// Rasterizes the unique glyphs among `requests`, returning one [`Image`]
+/// per distinct [`GlyphKey`]. Requests that fail to rasterize (e.g. an
+/// empty glyph) are simply absent from the result.
+#[cfg(feature = "rayon")]
+pub fn rasterize_batch(requests: &[RasterRequest], antialias: Antialias) -> HashMap<GlyphKey, Image> {
+    use rayon::prelude::*;
+
+    dedupe(requests)
+        .into_par_iter()
+        .map_init(
+            // One `ScaleContext` (and thus one family of `Scaler`s) per
+            // worker thread, since `Scaler` isn't `Send`.
+            || (ScaleContext::new(), GammaLut::default()),
+            |(scale_ctx, gamma_lut), request| {
+                let mut scaler = scale_ctx
+                    .builder(request.font)
+                    .size(request.font_size)
+                    .hint(request.hint)
+                    .build();
+
+                let image = render::rasterize(
+                    &mut scaler,
+                    request.key.glyph_id,
+                    request.key.offset(),
+                    antialias,
+                    gamma_lut,
+                )?;
+
+                Some((request.key, image))
+            },
+        )
+        .filter_map(|result| result)
+        .collect()
+}
+
+/// Single-threaded fallback used when the `rayon` feature is off.
+#[cfg(not(feature = "rayon"))]
+pub fn rasterize_batch(requests: &[RasterRequest], antialias: Antialias) -> HashMap<GlyphKey, Image> {
+    let mut scale_ctx = ScaleContext::new();
+    let gamma_lut = GammaLut::default();
+
+    dedupe(requests)
+        .into_iter()
+        .filter_map(|request| {
+            let mut scaler = scale_ctx
+                .builder(request.font)
+                .size(request.font_size)
+                .hint(request.hint)
+                .build();
+
+            let image = render::rasterize(
+                &mut scaler,
+                request.key.glyph_id,
+                request.key.offset(),
+                antialias,
+                &gamma_lut,
+            )?;
+
+            Some((request.key, image))
+        })
+        .collect()
+}
+
+/// Keeps only the first request for each distinct [`GlyphKey`].
+fn dedupe<'r, 'a>(requests: &'r [RasterRequest<'a>]) -> Vec<&'r RasterRequest<'a>> {
+    let mut seen = HashSet::new();
+    requests.iter().filter(|request| seen.insert(request.key)).collect()
+}
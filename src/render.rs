@@ -0,0 +1,200 @@
+//! Rasterization of a single glyph into coverage bytes, with a choice of
+//! antialiasing strategy.
+
+use swash::scale::image::{Content, Image};
+use swash::scale::{Render, Scaler, Source, StrikeWith};
+use swash::zeno::{Format, Transform, Vector};
+
+/// Antialiasing strategy used when rasterizing a glyph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Antialias {
+    /// Single coverage channel, smeared across R/G/B. Cheapest, blurriest.
+    #[default]
+    Grayscale,
+    /// Per-channel (R, G, B) coverage for horizontal-RGB LCD subpixel
+    /// layouts, gamma-corrected before compositing.
+    Subpixel,
+    /// Coverage thresholded to fully on/off, no antialiasing at all.
+    Mono,
+}
+
+impl Antialias {
+    /// Number of coverage bytes produced per pixel by this mode. Matches
+    /// zeno's own `Format::buffer_size`: a single coverage byte for
+    /// `Alpha`, and 4 bytes (R, G, B coverage plus a trailing byte zeno
+    /// pads every subpixel texel out to) for `Subpixel`.
+    pub fn bytes_per_pixel(self) -> u32 {
+        match self {
+            Antialias::Grayscale | Antialias::Mono => 1,
+            Antialias::Subpixel => 4,
+        }
+    }
+}
+
+/// A 256-entry-per-channel gamma-correction lookup table, applied to
+/// rasterized coverage bytes before compositing to sharpen subpixel-
+/// rendered text on LCD panels.
+pub struct GammaLut {
+    tables: [[u8; 256]; 3],
+}
+
+impl GammaLut {
+    /// Builds a LUT from a single gamma shared by all three channels.
+    pub fn new(gamma: f32) -> Self {
+        Self::with_channels([gamma; 3], [0.0; 3], [1.0; 3])
+    }
+
+    /// Builds a LUT with independent gamma, brightness and contrast per
+    /// channel, for per-subpixel tuning (e.g. blue tends to need a
+    /// different correction than red on most LCD panels).
+    pub fn with_channels(gamma: [f32; 3], brightness: [f32; 3], contrast: [f32; 3]) -> Self {
+        let mut tables = [[0u8; 256]; 3];
+
+        for (channel, table) in tables.iter_mut().enumerate() {
+            for (c, entry) in table.iter_mut().enumerate() {
+                let normalized = c as f32 / 255.0;
+                let corrected = normalized.powf(1.0 / gamma[channel]);
+                let adjusted = (corrected - 0.5) * contrast[channel] + 0.5 + brightness[channel];
+                *entry = (adjusted.clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+        }
+
+        Self { tables }
+    }
+
+    pub fn apply(&self, channel: usize, coverage: u8) -> u8 {
+        self.tables[channel][coverage as usize]
+    }
+}
+
+impl Default for GammaLut {
+    fn default() -> Self {
+        // sRGB-ish default gamma used by most subpixel-rendering
+        // implementations.
+        Self::new(2.2)
+    }
+}
+
+/// Rasterizes a single glyph with the given antialiasing strategy. The
+/// returned image's `data` is gamma-corrected in place when
+/// [`Antialias::Subpixel`] is used.
+pub fn rasterize(
+    scaler: &mut Scaler,
+    glyph_id: u16,
+    offset: Vector,
+    antialias: Antialias,
+    gamma_lut: &GammaLut,
+) -> Option<Image> {
+    rasterize_transformed(scaler, glyph_id, offset, None, antialias, gamma_lut)
+}
+
+/// Same as [`rasterize`], but additionally applies `transform` to the
+/// glyph before rasterizing it — used for synthetic oblique/italic, which
+/// shears the outline rather than changing the coverage bytes afterward.
+pub fn rasterize_transformed(
+    scaler: &mut Scaler,
+    glyph_id: u16,
+    offset: Vector,
+    transform: Option<Transform>,
+    antialias: Antialias,
+    gamma_lut: &GammaLut,
+) -> Option<Image> {
+    let mut image = render(scaler, glyph_id, offset, transform, antialias)?;
+    apply_antialias(&mut image.data, antialias, gamma_lut);
+    Some(image)
+}
+
+/// Builds a `Render` job for `glyph_id` with the sources every rasterizer in
+/// this module shares (color tables first, falling back to the plain
+/// outline), requesting the coverage format matching `antialias`.
+fn render(
+    scaler: &mut Scaler,
+    glyph_id: u16,
+    offset: Vector,
+    transform: Option<Transform>,
+    antialias: Antialias,
+) -> Option<Image> {
+    let format = match antialias {
+        Antialias::Grayscale | Antialias::Mono => Format::Alpha,
+        Antialias::Subpixel => Format::Subpixel,
+    };
+
+    let mut render = Render::new(&[
+        Source::ColorOutline(0),
+        Source::ColorBitmap(StrikeWith::BestFit),
+        Source::Outline,
+    ]);
+    render.format(format).offset(offset);
+
+    if let Some(transform) = transform {
+        render.transform(Some(transform));
+    }
+
+    render.render(scaler, glyph_id)
+}
+
+/// Applies `antialias`'s coverage post-processing (mono thresholding or
+/// subpixel gamma correction) to rasterized bytes in place.
+fn apply_antialias(data: &mut [u8], antialias: Antialias, gamma_lut: &GammaLut) {
+    match antialias {
+        Antialias::Grayscale => {}
+        Antialias::Mono => {
+            for coverage in data.iter_mut() {
+                *coverage = if *coverage >= 128 { 255 } else { 0 };
+            }
+        }
+        Antialias::Subpixel => {
+            // zeno writes subpixel coverage at a 4-byte stride (R, G, B,
+            // plus a trailing byte it doesn't use for coverage); only the
+            // first three are gamma-corrected.
+            for pixel in data.chunks_exact_mut(4) {
+                pixel[0] = gamma_lut.apply(0, pixel[0]);
+                pixel[1] = gamma_lut.apply(1, pixel[1]);
+                pixel[2] = gamma_lut.apply(2, pixel[2]);
+            }
+        }
+    }
+}
+
+/// A glyph rasterized through [`rasterize_color`], tagged with whether
+/// swash produced pre-composited color (COLR/CBDT/SVG) or plain coverage.
+/// `rasterize`/`rasterize_transformed` treat every result as
+/// [`Format::Alpha`] or [`Format::Subpixel`] coverage, even when swash hit a
+/// color table; this is the one entry point that reports RGBA for
+/// emoji/color-font glyphs as color instead.
+pub enum RenderedGlyph {
+    /// Pre-composited RGBA8, straight (non-premultiplied) alpha, as swash
+    /// reports `Content::Color`. One entry per pixel, `width * height * 4`
+    /// bytes.
+    Color(Image),
+    /// Coverage-only bitmap, same byte layout `rasterize_transformed` would
+    /// have produced for `antialias`.
+    Mask(Image),
+}
+
+/// Rasterizes a glyph the same way as [`rasterize_transformed`], except the
+/// result is inspected afterward: `Render` tries `Source::ColorOutline` and
+/// `Source::ColorBitmap` before falling back to `Source::Outline`, and tags
+/// a successful color hit as `Content::Color` in the returned image
+/// regardless of the requested `Format`. `rasterize_transformed` throws that
+/// tag away by treating every image as coverage; this keeps it and reports
+/// it via the returned [`RenderedGlyph`] variant. A glyph with no color
+/// table still renders as a normal coverage mask, with `antialias`'s usual
+/// post-processing applied.
+pub fn rasterize_color(
+    scaler: &mut Scaler,
+    glyph_id: u16,
+    offset: Vector,
+    transform: Option<Transform>,
+    antialias: Antialias,
+    gamma_lut: &GammaLut,
+) -> Option<RenderedGlyph> {
+    let mut image = render(scaler, glyph_id, offset, transform, antialias)?;
+
+    if image.content == Content::Color {
+        return Some(RenderedGlyph::Color(image));
+    }
+
+    apply_antialias(&mut image.data, antialias, gamma_lut);
+    Some(RenderedGlyph::Mask(image))
+}
@@ -0,0 +1,124 @@
+//! Synthetic styling (bold/oblique) and variable-font axis support.
+//!
+//! The scaler/shaper builders elsewhere in this crate only ever set `size`
+//! and `hint`. This module adds synthetic styling for faces that don't
+//! have a dedicated bold/italic variant on disk — an oblique shear applied
+//! to the glyph transform and an emboldening pass that dilates rasterized
+//! coverage — plus forwarding variable-font axis values (`wght`, `slnt`,
+//! ...) into both the shaping and scaling builders.
+
+use swash::zeno::Transform;
+use swash::Setting;
+
+/// A single `(tag, value)` variable-font axis setting, e.g. `("wght", 700.0)`.
+pub type Variation = Setting<f32>;
+
+/// Synthetic styling and variation coordinates requested for a glyph run.
+/// Passed alongside a [`GlyphKey`](crate::glyph_cache::GlyphKey) so cached
+/// entries never collide across styles.
+#[derive(Debug, Clone, Default)]
+pub struct GlyphStyle {
+    /// Shear angle in degrees applied for synthetic oblique/italic (0 =
+    /// upright).
+    pub oblique_degrees: f32,
+    /// Dilation, in pixels, applied to rasterized coverage to fake a bold
+    /// weight on a regular face.
+    pub embolden_px: f32,
+    /// Variable-font axis values, forwarded verbatim into the
+    /// `ShapeContext`/`ScaleContext` builders' `.variations(...)`.
+    pub variations: Vec<Variation>,
+}
+
+impl GlyphStyle {
+    /// The transform to apply to the glyph outline/bitmap for
+    /// [`Self::oblique_degrees`], or `None` when upright (the common case,
+    /// so callers can skip `Render::transform` entirely).
+    pub fn transform(&self) -> Option<Transform> {
+        if self.oblique_degrees == 0.0 {
+            return None;
+        }
+
+        // A simple shear along x, proportional to y: the same trick used
+        // to fake italics from an upright face when no slanted design
+        // exists.
+        let shear = self.oblique_degrees.to_radians().tan();
+        Some(Transform {
+            xx: 1.0,
+            yx: shear,
+            xy: 0.0,
+            yy: 1.0,
+            x: 0.0,
+            y: 0.0,
+        })
+    }
+}
+
+/// Quantized, hashable fingerprint of a [`GlyphStyle`], suitable for
+/// embedding in a cache key so styled and unstyled glyphs (or different
+/// styles of the same glyph) never collide in the atlas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct StyleKey {
+    oblique_tenths: i16,
+    embolden_fixed: u16,
+    variations_fingerprint: u64,
+}
+
+impl StyleKey {
+    pub fn new(style: &GlyphStyle) -> Self {
+        Self {
+            oblique_tenths: (style.oblique_degrees * 10.0).round() as i16,
+            embolden_fixed: (style.embolden_px * 64.0).round() as u16,
+            variations_fingerprint: fingerprint_variations(&style.variations),
+        }
+    }
+}
+
+/// A hash over the variation settings, since `f32` doesn't implement
+/// `Hash` and pulling each axis into the key type individually would
+/// require knowing every possible tag up front. `Setting`'s `Debug` output
+/// includes both the tag and the value, so this is sufficient to tell two
+/// different variation sets apart.
+fn fingerprint_variations(variations: &[Variation]) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for setting in variations {
+        format!("{setting:?}").hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Dilates rasterized coverage by `radius_px` pixels (a max filter over a
+/// square window), the simplest way to fake a heavier weight on a face
+/// that has no bold variant.
+pub fn embolden(data: &mut [u8], width: usize, height: usize, bytes_per_pixel: usize, radius_px: u32) {
+    if radius_px == 0 || width == 0 || height == 0 {
+        return;
+    }
+
+    let radius = radius_px as isize;
+    let source = data.to_vec();
+
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            for channel in 0..bytes_per_pixel {
+                let mut max = 0u8;
+
+                for dy in -radius..=radius {
+                    for dx in -radius..=radius {
+                        let (sx, sy) = (x + dx, y + dy);
+                        if sx < 0 || sy < 0 || sx >= width as isize || sy >= height as isize {
+                            continue;
+                        }
+
+                        let idx = (sy as usize * width + sx as usize) * bytes_per_pixel + channel;
+                        max = max.max(source[idx]);
+                    }
+                }
+
+                data[(y as usize * width + x as usize) * bytes_per_pixel + channel] = max;
+            }
+        }
+    }
+}
+